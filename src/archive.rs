@@ -1,13 +1,14 @@
 use super::util;
+use bytes::Bytes;
 use std::collections::HashMap;
 
-// some possible issues w/ current design: excessive copying??
-// a lot of the interfacing is a clear copy on HashMap.. maybe we can
-// leverage that.
-
 #[derive(Clone, Debug, Default)]
 pub struct Archive {
-    pub(crate) entries: HashMap<u32, Vec<u8>>,
+    pub(crate) entries: HashMap<u32, Bytes>,
+    // `entry_hash` is one-way, so an entry's original name is only known if
+    // it was supplied through `insert`/`get_mut`, or resolved with
+    // `identify`. Entries loaded straight from disk have none of these.
+    pub(crate) names: HashMap<u32, String>,
 }
 
 impl Archive {
@@ -29,18 +30,20 @@ impl Archive {
     }
 
     /// Removes an entry.
-    pub fn remove<S>(&mut self, key: S) -> Option<Vec<u8>>
+    pub fn remove<S>(&mut self, key: S) -> Option<Bytes>
     where
         S: Into<String>,
     {
         let hash = util::entry_hash(key.into());
 
+        self.names.remove(&hash);
         self.entries.remove(&hash)
     }
 
     /// Clears the entry table of this archive
     pub fn clear(&mut self) {
         self.entries.clear();
+        self.names.clear();
     }
 
     /// Returns the number of entries this archive contains.
@@ -52,4 +55,91 @@ impl Archive {
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
+
+    /// Returns an iterator over `(hash, data)` pairs for every entry,
+    /// borrowing each entry's body rather than cloning it.
+    pub fn entries(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.entries.iter().map(|(hash, data)| (*hash, data.as_ref()))
+    }
+
+    /// Returns an iterator over the hash of every entry in this archive.
+    /// Named `keys` rather than `names` because `entry_hash` is one-way -
+    /// see `entries_named`/`name_of` for the entries whose original name is
+    /// actually known.
+    pub fn keys(&self) -> impl Iterator<Item = u32> + '_ {
+        self.entries.keys().copied()
+    }
+
+    /// Removes every entry from this archive, returning an iterator over
+    /// the `(hash, data)` pairs that were removed.
+    pub fn drain(&mut self) -> impl Iterator<Item = (u32, Bytes)> + '_ {
+        self.names.clear();
+        self.entries.drain()
+    }
+
+    /// Returns the original name of an entry, if it is known.
+    pub fn name_of(&self, hash: u32) -> Option<&str> {
+        self.names.get(&hash).map(String::as_str)
+    }
+
+    /// Returns an iterator over `(name, data)` pairs for every entry whose
+    /// original name is known.
+    pub fn entries_named(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.names.iter().filter_map(move |(hash, name)| {
+            self.entries.get(hash).map(|data| (name.as_str(), data.as_ref()))
+        })
+    }
+
+    /// Attempts to identify the entries of this archive that don't yet have
+    /// a known name, by hashing each candidate in `wordlist` with
+    /// `entry_hash` and recording it against any entry it matches. Returns
+    /// the hashes that remain unidentified afterwards.
+    pub fn identify<I, S>(&mut self, wordlist: I) -> Vec<u32>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for candidate in wordlist {
+            let candidate = candidate.into();
+            let hash = util::entry_hash(candidate.clone());
+
+            if self.entries.contains_key(&hash) {
+                self.names.entry(hash).or_insert(candidate);
+            }
+        }
+
+        self.entries
+            .keys()
+            .filter(|hash| !self.names.contains_key(*hash))
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_and_named_entries() {
+        let logo_hash = util::entry_hash("logo.tga".into());
+        let testing_hash = util::entry_hash("testing".into());
+
+        let mut archive = Archive::new();
+        archive.entries.insert(logo_hash, Bytes::from_static(b"tga"));
+        archive.entries.insert(testing_hash, Bytes::from_static(b"test"));
+
+        // "nope" doesn't hash to either entry, so testing_hash stays unidentified
+        let unidentified = archive.identify(vec!["logo.tga", "nope"]);
+        assert_eq!(unidentified, vec![testing_hash]);
+
+        assert_eq!(archive.name_of(logo_hash), Some("logo.tga"));
+        assert_eq!(archive.name_of(testing_hash), None);
+
+        let named: Vec<(&str, &[u8])> = archive.entries_named().collect();
+        assert_eq!(named, vec![("logo.tga", b"tga".as_ref())]);
+
+        archive.remove("logo.tga");
+        assert_eq!(archive.name_of(logo_hash), None);
+    }
 }
@@ -0,0 +1,41 @@
+use std::io;
+
+/// Errors that can occur while reading or writing an [`crate::Archive`].
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The buffer ran out before the expected number of bytes could be
+    /// read.
+    #[error("unexpected EOF: needed {needed} bytes but only {remaining} remain")]
+    UnexpectedEof { needed: usize, remaining: usize },
+
+    /// The entry table, or the entry data it describes, extends past the
+    /// end of the archive data.
+    #[error("truncated entry data: entry table describes more data than is present")]
+    TruncatedEntryData,
+
+    /// The archive, or one of its entries, could not be decompressed.
+    #[error("failed to decompress data")]
+    DecompressionFailed,
+
+    /// The number of entries in the archive exceeds what a `u16` can
+    /// address.
+    #[error("entry count overflow: archive contains too many entries")]
+    EntryCountOverflow,
+
+    /// A size destined for one of the format's 3-byte size fields exceeds
+    /// the 16,777,215 bytes (2^24 - 1) those fields can address.
+    #[error("size field overflow: {size} bytes exceeds the 3-byte field limit of 16777215 bytes")]
+    SizeFieldOverflow { size: usize },
+
+    /// The decompressed size recorded in a header or entry record did not
+    /// match the length actually produced by decompression.
+    #[error("decompressed size mismatch: header said {expected} bytes, got {actual}")]
+    SizeMismatch { expected: usize, actual: usize },
+
+    /// An I/O error occurred while reading or writing the archive file.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A specialized [`Result`](std::result::Result) for archive operations.
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,9 +1,11 @@
 mod archive;
+mod error;
 mod read;
 mod util;
 mod write;
 
 pub use archive::*;
+pub use error::*;
 pub use read::*;
 pub use write::*;
 
@@ -1,6 +1,5 @@
-use super::{util, Archive};
+use super::{util, Archive, Error, Result};
 
-use anyhow::{anyhow, Error, Result};
 use bytes::{Buf, Bytes};
 use std::convert::TryFrom;
 use std::fs::File;
@@ -8,19 +7,21 @@ use std::io::prelude::*;
 use std::path::Path;
 
 pub trait ReadArchive {
-    /// Gets a reference to an entry from an archive, if it exists.
-    fn get<S>(&self, key: S) -> Option<&Vec<u8>>
+    /// Gets a reference to an entry from an archive, if it exists. Unlike
+    /// `get_mut`, this takes `&self` and so cannot record `key` in the
+    /// archive's name table - use `get_mut` or `insert` if that matters.
+    fn get<S>(&self, key: S) -> Option<&Bytes>
     where
         S: Into<String>;
 
     /// Gets a mutable reference to an entry from an archive, if it exists.
-    fn get_mut<S>(&mut self, key: S) -> Option<&mut Vec<u8>>
+    fn get_mut<S>(&mut self, key: S) -> Option<&mut Bytes>
     where
         S: Into<String>;
 }
 
 impl ReadArchive for Archive {
-    fn get<S>(&self, key: S) -> Option<&Vec<u8>>
+    fn get<S>(&self, key: S) -> Option<&Bytes>
     where
         S: Into<String>,
     {
@@ -29,11 +30,16 @@ impl ReadArchive for Archive {
         self.entries.get(&hash)
     }
 
-    fn get_mut<S>(&mut self, key: S) -> Option<&mut Vec<u8>>
+    fn get_mut<S>(&mut self, key: S) -> Option<&mut Bytes>
     where
         S: Into<String>,
     {
-        let hash = util::entry_hash(key.into());
+        let key = key.into();
+        let hash = util::entry_hash(key.clone());
+
+        if self.entries.contains_key(&hash) {
+            self.names.insert(hash, key);
+        }
 
         self.entries.get_mut(&hash)
     }
@@ -54,21 +60,31 @@ impl Archive {
 
     fn read_headers(&mut self, mut buffer: Bytes) -> Result<Bytes> {
         if buffer.remaining() < 6 {
-            return Err(anyhow!("Unexpected EOF: Unable to read archive headers"));
+            return Err(Error::UnexpectedEof {
+                needed: 6,
+                remaining: buffer.remaining(),
+            });
         }
 
-        let decompressed_size = buffer.get_int(3);
-        let compressed_size = buffer.get_int(3);
+        let decompressed_size = buffer.get_int(3) as usize;
+        let compressed_size = buffer.get_int(3) as usize;
 
         if decompressed_size != compressed_size {
             // the archive requires decompressing, decompress it and make
             // sure there aren't any errors..
-            match util::decompress(buffer) {
-                Some(decompressed) => Ok(decompressed),
-                None => Err(anyhow!(
-                    "Invalid payload: Unable to decompress bzip2 stream"
-                )),
+            let codec = util::Codec::detect(&buffer);
+
+            let decompressed =
+                util::decompress(codec, buffer).ok_or(Error::DecompressionFailed)?;
+
+            if decompressed.len() != decompressed_size {
+                return Err(Error::SizeMismatch {
+                    expected: decompressed_size,
+                    actual: decompressed.len(),
+                });
             }
+
+            Ok(decompressed)
         } else {
             Ok(buffer)
         }
@@ -76,37 +92,52 @@ impl Archive {
 
     fn read_entries(&mut self, mut buffer: Bytes) -> Result<()> {
         if buffer.remaining() < 2 {
-            return Err(anyhow!(
-                "Unexpected EOF: Unable to read archive entry count"
-            ));
+            return Err(Error::UnexpectedEof {
+                needed: 2,
+                remaining: buffer.remaining(),
+            });
         }
 
         let entry_count = buffer.get_u16();
-        let data_start = (entry_count * 10) as usize;
+        let data_start = entry_count as usize * 10;
+
+        if data_start > buffer.remaining() {
+            return Err(Error::TruncatedEntryData);
+        }
+
         let mut data_buffer = buffer.split_off(data_start);
 
         for _ in 0..entry_count {
             if buffer.remaining() < 10 {
-                return Err(anyhow!(
-                    "Unexpected EOF: Unable to read archive entry count"
-                ));
+                return Err(Error::UnexpectedEof {
+                    needed: 10,
+                    remaining: buffer.remaining(),
+                });
             }
             let hash = buffer.get_int(4) as u32;
             let decompressed_size = buffer.get_int(3) as usize;
             let compressed_size = buffer.get_int(3) as usize;
 
+            if compressed_size > data_buffer.remaining() {
+                return Err(Error::TruncatedEntryData);
+            }
+
             let data = data_buffer.split_to(compressed_size);
 
             if decompressed_size != compressed_size {
-                let data = match util::decompress(data) {
-                    Some(decompressed) => Ok(decompressed),
-                    None => Err(anyhow!(
-                        "Invalid payload: Unable to decompress bzip2 stream"
-                    )),
-                }?;
-                self.entries.insert(hash, data.to_vec());
+                let codec = util::Codec::detect(&data);
+                let data = util::decompress(codec, data).ok_or(Error::DecompressionFailed)?;
+
+                if data.len() != decompressed_size {
+                    return Err(Error::SizeMismatch {
+                        expected: decompressed_size,
+                        actual: data.len(),
+                    });
+                }
+
+                self.entries.insert(hash, data);
             } else {
-                self.entries.insert(hash, data.to_vec());
+                self.entries.insert(hash, data);
             }
         }
 
@@ -114,28 +145,32 @@ impl Archive {
     }
 }
 
-impl TryFrom<&Path> for Archive {
-    type Error = Error;
-
-    /// Attempts to read an existing archive from disk into a new archive.
-    fn try_from(path: &Path) -> Result<Self, self::Error> {
-        let mut file = File::open(path)?;
+impl Archive {
+    /// Reads an archive from any `Read` source - a file, socket, or another
+    /// decompressor - without requiring the caller to buffer it first.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
         let mut data = Vec::new();
-        // read the file to a vector
-        file.read_to_end(&mut data)?;
-
-        // then place that vector into a Bytes object
-        let buffer = Bytes::from(data);
+        reader.read_to_end(&mut data)?;
 
         let mut archive = Archive::new();
 
-        // now we can attempt to read the archive's headers
-        archive.read_archive(buffer)?;
+        archive.read_archive(Bytes::from(data))?;
 
         Ok(archive)
     }
 }
 
+impl TryFrom<&Path> for Archive {
+    type Error = Error;
+
+    /// Attempts to read an existing archive from disk into a new archive.
+    fn try_from(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+
+        Archive::from_reader(file)
+    }
+}
+
 #[cfg(test)]
 mod read_tests {
     #[test]
@@ -179,4 +214,48 @@ mod read_tests {
         archive.clear();
         assert_eq!(archive.len(), 0);
     }
+
+    #[test]
+    fn test_from_reader_and_entry_iterators() {
+        use super::*;
+        use crate::WriteArchive;
+
+        let mut written = Archive::new();
+        written.insert("hello.world", b"hello world".to_vec());
+        written.insert("logo.tga", vec![7u8; 4096]);
+        written.insert("testing", Vec::new());
+
+        let path = std::env::temp_dir().join("jagged_from_reader_and_entry_iterators.jag");
+        written.save(&path).unwrap();
+
+        let file = File::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // from_reader should read the same archive as try_from(&Path)
+        let mut archive = Archive::from_reader(file).unwrap();
+        assert_eq!(archive.len(), 3);
+
+        let keys: Vec<u32> = archive.keys().collect();
+        assert_eq!(keys.len(), archive.len());
+
+        let entry_hashes: Vec<u32> = archive.entries().map(|(hash, _)| hash).collect();
+        assert_eq!(entry_hashes.len(), keys.len());
+        assert!(entry_hashes.iter().all(|hash| keys.contains(hash)));
+
+        let drained: Vec<(u32, Bytes)> = archive.drain().collect();
+        assert_eq!(drained.len(), 3);
+        assert_eq!(archive.len(), 0);
+    }
+
+    #[test]
+    fn test_truncated_archive_errors() {
+        use super::*;
+
+        // claims 6 entries worth of table data but supplies none
+        let buffer = Bytes::from(vec![0u8, 6]);
+        let mut archive = Archive::new();
+
+        let err = archive.read_entries(buffer).unwrap_err();
+        assert!(matches!(err, Error::TruncatedEntryData));
+    }
 }
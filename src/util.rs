@@ -1,24 +1,132 @@
 use bytes::Bytes;
 use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use bzip2::Compression as BzCompression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression as GzCompression;
 use std::io::prelude::*;
 
-pub(crate) fn decompress(data: Bytes) -> Option<Bytes> {
-    // The required header, "BZh1", that is missing in jag archives
-    // must be appended
-    let mut concatenated = vec![66u8, 90, 104, 49];
-    concatenated.extend(data.into_iter());
+/// The gzip magic number, per RFC 1952.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
-    let mut decompressor = BzDecoder::new(concatenated.as_slice());
-    let mut decompressed_data = Vec::new();
+/// The codec a header or entry's data block is compressed with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Codec {
+    /// Not compressed; the data block is used as-is.
+    Store,
+    /// bzip2, possibly with the leading "BZh1" header stripped the way JAG
+    /// archives store it.
+    Bzip2,
+    /// gzip, identified by its magic number.
+    Gzip,
+}
+
+impl Codec {
+    /// Sniffs the leading bytes of `data` to determine which codec it was
+    /// compressed with. A buffer already starting with the gzip magic, or a
+    /// full bzip2 header (`"BZh"` followed by a block-size digit), is
+    /// decompressed as-is; anything else is assumed to be a headerless
+    /// bzip2 stream, since JAG strips the `"BZh1"` header before storing it.
+    pub(crate) fn detect(data: &[u8]) -> Codec {
+        if data.starts_with(&GZIP_MAGIC) {
+            Codec::Gzip
+        } else {
+            Codec::Bzip2
+        }
+    }
+}
+
+/// Decompresses `data` using the given `codec`.
+///
+/// we don't need to know the specific error, just that decompression
+/// failed, hence why this func returns an Option rather than Result.
+pub(crate) fn decompress(codec: Codec, data: Bytes) -> Option<Bytes> {
+    match codec {
+        Codec::Store => Some(data),
+        Codec::Gzip => {
+            let mut decompressor = GzDecoder::new(data.as_ref());
+            let mut decompressed_data = Vec::new();
+
+            match decompressor.read_to_end(&mut decompressed_data) {
+                Ok(_) => Some(Bytes::from(decompressed_data)),
+                Err(_) => None,
+            }
+        }
+        Codec::Bzip2 => {
+            let has_full_header =
+                data.len() >= 4 && data.starts_with(b"BZh") && data[3].is_ascii_digit();
+
+            let prefixed;
+            let source: &[u8] = if has_full_header {
+                data.as_ref()
+            } else {
+                // The required header, "BZh1", that is missing in jag
+                // archives must be appended
+                prefixed = [&[66u8, 90, 104, 49][..], data.as_ref()].concat();
+                &prefixed
+            };
+
+            let mut decompressor = BzDecoder::new(source);
+            let mut decompressed_data = Vec::new();
+
+            match decompressor.read_to_end(&mut decompressed_data) {
+                Ok(_) => Some(Bytes::from(decompressed_data)),
+                Err(_) => None,
+            }
+        }
+    }
+}
+
+/// Compresses `data` into a bzip2 stream with the leading "BZh1" header
+/// stripped, the inverse of the header-less case `decompress` handles. The
+/// block size is pinned to level 1 so the stripped header matches what
+/// `decompress` re-adds.
+pub(crate) fn compress(data: &[u8]) -> Option<Vec<u8>> {
+    let mut compressor = BzEncoder::new(Vec::new(), BzCompression::fast());
+
+    if compressor.write_all(data).is_err() {
+        return None;
+    }
 
-    // we don't need to know the specific error, just that decompression
-    // failed, hence why this func returns an Option rather than Result.
-    match decompressor.read_to_end(&mut decompressed_data) {
-        Ok(_) => Some(Bytes::from(decompressed_data)),
+    match compressor.finish() {
+        Ok(compressed) => Some(compressed[4..].to_vec()),
         Err(_) => None,
     }
 }
 
+/// Compresses `data` with gzip.
+pub(crate) fn compress_gzip(data: &[u8]) -> Option<Vec<u8>> {
+    let mut compressor = GzEncoder::new(Vec::new(), GzCompression::default());
+
+    if compressor.write_all(data).is_err() {
+        return None;
+    }
+
+    compressor.finish().ok()
+}
+
+/// Compresses `data` with every available codec and returns whichever
+/// codec yields the smallest result, falling back to `Codec::Store` (the
+/// data unchanged) if nothing shrinks it.
+pub(crate) fn compress_best(data: &[u8]) -> (Codec, Vec<u8>) {
+    let mut best = (Codec::Store, data.to_vec());
+
+    if let Some(bzip2) = compress(data) {
+        if bzip2.len() < best.1.len() {
+            best = (Codec::Bzip2, bzip2);
+        }
+    }
+
+    if let Some(gzip) = compress_gzip(data) {
+        if gzip.len() < best.1.len() {
+            best = (Codec::Gzip, gzip);
+        }
+    }
+
+    best
+}
+
 pub(crate) fn entry_hash(entry: String) -> u32 {
     use std::num::Wrapping;
 
@@ -51,4 +159,32 @@ mod util_tests {
             assert_eq!(super::entry_hash(k.into()), v);
         }
     }
+
+    #[test]
+    fn test_codec_detection_and_round_trip() {
+        use super::{compress_best, decompress, Codec};
+
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(16);
+
+        assert_eq!(Codec::detect(&data), Codec::Bzip2);
+
+        let (codec, compressed) = compress_best(&data);
+        assert_eq!(Codec::detect(&compressed), codec);
+
+        let decompressed = decompress(codec, compressed.into()).unwrap();
+        assert_eq!(decompressed, data.as_slice());
+    }
+
+    #[test]
+    fn test_gzip_detection_and_round_trip() {
+        use super::{compress_gzip, decompress, Codec};
+
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(16);
+        let compressed = compress_gzip(&data).unwrap();
+
+        assert_eq!(Codec::detect(&compressed), Codec::Gzip);
+
+        let decompressed = decompress(Codec::Gzip, compressed.into()).unwrap();
+        assert_eq!(decompressed, data.as_slice());
+    }
 }
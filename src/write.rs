@@ -1,10 +1,26 @@
-use super::{util, Archive};
+use super::{util, Archive, Error, Result};
 
-use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 
+/// The largest value a 3-byte size field can hold (2^24 - 1).
+const MAX_SIZE_FIELD: usize = 0xFF_FFFF;
+
+/// Writes `size` into `buf` as a 3-byte big-endian field, erroring instead
+/// of silently truncating it if it doesn't fit.
+fn put_size_field(buf: &mut BytesMut, size: usize) -> Result<()> {
+    if size > MAX_SIZE_FIELD {
+        return Err(Error::SizeFieldOverflow { size });
+    }
+
+    buf.put_uint(size as u64, 3);
+
+    Ok(())
+}
+
 pub trait WriteArchive {
     /// Writes an archive to disk with the given path. If a file is already
     /// present at the given path, it is simply overwritten.
@@ -12,7 +28,7 @@ pub trait WriteArchive {
 
     /// Inserts a key-value pair. If the archive already contains the given
     /// key, the previous value for that key is returned.
-    fn insert<S>(&mut self, key: S, value: Vec<u8>) -> Option<Vec<u8>>
+    fn insert<S>(&mut self, key: S, value: Vec<u8>) -> Option<Bytes>
     where
         S: Into<String>;
 }
@@ -27,22 +43,139 @@ impl WriteArchive for Archive {
         Ok(())
     }
 
-    fn insert<S>(&mut self, key: S, value: Vec<u8>) -> Option<Vec<u8>>
+    fn insert<S>(&mut self, key: S, value: Vec<u8>) -> Option<Bytes>
     where
         S: Into<String>,
     {
-        let hash = util::entry_hash(key.into());
+        let key = key.into();
+        let hash = util::entry_hash(key.clone());
 
-        self.entries.insert(hash, value)
+        self.names.insert(hash, key);
+        self.entries.insert(hash, Bytes::from(value))
     }
 }
 
 impl Archive {
     fn write_to_file(&self, file: &mut File) -> Result<()> {
+        let (decompressed_size, compressed_size, payload) = self.generate_data_block()?;
+
+        let mut header = BytesMut::with_capacity(6);
+        put_size_field(&mut header, decompressed_size)?;
+        put_size_field(&mut header, compressed_size)?;
+
+        file.write_all(&header)?;
+        file.write_all(&payload)?;
+
         Ok(())
     }
 
-    fn generate_data_block(&self) -> Option<Vec<u8>> {
-        None
+    /// Builds the archive payload, trying both JAG layouts supported by the
+    /// reader - whole-archive compression with raw entries, and a raw
+    /// archive with per-entry compression - and keeping whichever is
+    /// smaller. Returns `(decompressed_size, compressed_size, payload)`,
+    /// where `payload` is exactly what follows the 6-byte archive header.
+    fn generate_data_block(&self) -> Result<(usize, usize, Vec<u8>)> {
+        let raw_entries = self.build_entry_block(false)?;
+        let per_entry_compressed = self.build_entry_block(true)?;
+
+        let (_, whole_archive_compressed) = util::compress_best(&raw_entries);
+
+        // whole-archive compression: raw entry table/data, compressed once.
+        let whole_archive_candidate = (
+            raw_entries.len(),
+            whole_archive_compressed.len(),
+            whole_archive_compressed,
+        );
+
+        // raw archive: entries individually compressed where it helps.
+        let per_entry_candidate = (
+            per_entry_compressed.len(),
+            per_entry_compressed.len(),
+            per_entry_compressed,
+        );
+
+        let chosen = if whole_archive_candidate.1 < per_entry_candidate.1 {
+            whole_archive_candidate
+        } else {
+            per_entry_candidate
+        };
+
+        Ok(chosen)
+    }
+
+    /// Builds the entry count, entry table, and concatenated entry data for
+    /// this archive. When `compress_entries` is `true`, each entry is
+    /// individually compressed with whichever codec makes it smaller.
+    fn build_entry_block(&self, compress_entries: bool) -> Result<Vec<u8>> {
+        let entry_count =
+            u16::try_from(self.entries.len()).map_err(|_| Error::EntryCountOverflow)?;
+
+        let mut table = BytesMut::with_capacity(self.entries.len() * 10);
+        let mut data = Vec::new();
+
+        for (hash, decompressed) in self.entries.iter() {
+            let compressed = if compress_entries {
+                let (_, compressed) = util::compress_best(decompressed);
+                Some(compressed)
+            } else {
+                None
+            };
+
+            let body: &[u8] = match &compressed {
+                Some(compressed) if compressed.len() < decompressed.len() => compressed.as_slice(),
+                _ => decompressed.as_ref(),
+            };
+
+            table.put_uint(*hash as u64, 4);
+            put_size_field(&mut table, decompressed.len())?;
+            put_size_field(&mut table, body.len())?;
+
+            data.extend_from_slice(body);
+        }
+
+        let mut block = BytesMut::with_capacity(2 + table.len() + data.len());
+        block.put_u16(entry_count);
+        block.unsplit(table);
+        block.extend_from_slice(&data);
+
+        Ok(block.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod write_tests {
+    use super::*;
+    use crate::ReadArchive;
+
+    #[test]
+    fn test_round_trip() {
+        let mut archive = Archive::new();
+
+        archive.insert("hello.world", b"hello world".to_vec());
+        archive.insert("logo.tga", vec![7u8; 4096]);
+        archive.insert("testing", Vec::new());
+
+        let path = std::env::temp_dir().join("jagged_write_round_trip.jag");
+        archive.save(&path).unwrap();
+
+        let loaded = Archive::try_from(path.as_path()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.len(), archive.len());
+        assert_eq!(loaded.get("hello.world"), archive.get("hello.world"));
+        assert_eq!(loaded.get("logo.tga"), archive.get("logo.tga"));
+        assert_eq!(loaded.get("testing"), archive.get("testing"));
+    }
+
+    #[test]
+    fn test_oversized_entry_is_rejected() {
+        let mut archive = Archive::new();
+        archive.insert("huge.bin", vec![0u8; MAX_SIZE_FIELD + 1]);
+
+        let path = std::env::temp_dir().join("jagged_write_oversized_entry.jag");
+        let result = archive.save(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(Error::SizeFieldOverflow { .. })));
     }
 }